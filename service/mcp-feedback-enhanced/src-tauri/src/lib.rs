@@ -1,15 +1,102 @@
 use pyo3::prelude::*;
 use tauri::{Builder, Context, Manager};
 use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+
+use tauri_plugin_shell::process::CommandChild;
 
 // 全局狀態管理
 static APP_STATE: Mutex<Option<tauri::AppHandle>> = Mutex::new(None);
 
+// 目前正在運行的 sidecar 子行程，附上其世代編號以區分重啟
+static SIDECAR: Mutex<Option<(u64, CommandChild)>> = Mutex::new(None);
+
+// sidecar 世代計數器，每次 start_sidecar 遞增一次
+static SIDECAR_GEN: AtomicU64 = AtomicU64::new(0);
+
+/// 終止正在運行的 sidecar 子行程（若有）
+fn kill_sidecar() {
+    if let Some((_, child)) = SIDECAR.lock().unwrap().take() {
+        let _ = child.kill();
+    }
+}
+
 /// Tauri 應用程式狀態
+///
+/// 由於 `tauri::State<AppState>` 是不可變借用，欄位改用內部可變性
+/// （`Mutex` / `AtomicBool`），讓指令可以在執行期更新狀態。
 #[derive(Default)]
 struct AppState {
-    web_url: String,
-    desktop_mode: bool,
+    web_url: Mutex<String>,
+    desktop_mode: AtomicBool,
+    enable_tray: AtomicBool,
+    tray_labels: Mutex<TrayLabels>,
+}
+
+/// 系統匣選單的文字標籤
+struct TrayLabels {
+    show_hide: String,
+    reload: String,
+    quit: String,
+}
+
+impl Default for TrayLabels {
+    fn default() -> Self {
+        TrayLabels {
+            show_hide: "顯示/隱藏".to_string(),
+            reload: "重新載入".to_string(),
+            quit: "結束".to_string(),
+        }
+    }
+}
+
+/// 建立系統匣圖示與選單
+///
+/// 選單包含「顯示/隱藏」（切換 `main` 視窗可見性）、「重新載入」
+/// （重新導航到已儲存的 `web_url`）與「結束」三個項目。
+fn setup_tray(app: &tauri::AppHandle, labels: &TrayLabels) -> tauri::Result<()> {
+    use tauri::menu::{Menu, MenuItem};
+    use tauri::tray::TrayIconBuilder;
+
+    // 已存在同 id 的系統匣時不重複建立，保持冪等
+    if app.tray_by_id("main-tray").is_some() {
+        return Ok(());
+    }
+
+    let show_hide = MenuItem::with_id(app, "show_hide", &labels.show_hide, true, None::<&str>)?;
+    let reload = MenuItem::with_id(app, "reload", &labels.reload, true, None::<&str>)?;
+    let quit = MenuItem::with_id(app, "quit", &labels.quit, true, None::<&str>)?;
+    let menu = Menu::with_items(app, &[&show_hide, &reload, &quit])?;
+
+    TrayIconBuilder::with_id("main-tray")
+        .menu(&menu)
+        .on_menu_event(|app, event| match event.id.as_ref() {
+            "show_hide" => {
+                if let Some(window) = app.get_webview_window("main") {
+                    if window.is_visible().unwrap_or(false) {
+                        let _ = window.hide();
+                    } else {
+                        let _ = window.show();
+                        let _ = window.set_focus();
+                    }
+                }
+            }
+            "reload" => {
+                let url = app.state::<AppState>().web_url.lock().unwrap().clone();
+                if let (Some(window), Ok(parsed)) = (app.get_webview_window("main"), url.parse()) {
+                    let _ = window.navigate(parsed);
+                }
+            }
+            "quit" => {
+                // 先終止 sidecar，再結束，保證後端不會被遺留
+                kill_sidecar();
+                app.exit(0);
+            }
+            _ => {}
+        })
+        .build(app)?;
+
+    Ok(())
 }
 
 /// 生成 Tauri 上下文
@@ -29,11 +116,9 @@ pub fn create_tauri_builder() -> Builder<tauri::Wry> {
                 *state = Some(app.handle().clone());
             }
 
-            // 設置應用程式狀態
-            let _app_state = app.state::<AppState>();
-            {
-                // 這裡可以設置初始狀態
-            }
+            // 註：在 Python 嵌入路徑下，系統匣是在執行期由 set_tray_enabled
+            // 建立的（此時才取得 AppHandle），所以這裡不在 setup 階段建立；
+            // 獨立二進位則於 main.rs 的 setup 中強制啟用系統匣。
 
             println!("Tauri 應用程式已初始化");
             Ok(())
@@ -49,27 +134,54 @@ pub fn create_tauri_builder() -> Builder<tauri::Wry> {
 /// 獲取 Web URL
 #[tauri::command]
 fn get_web_url(state: tauri::State<AppState>) -> String {
-    state.web_url.clone()
+    state.web_url.lock().unwrap().clone()
 }
 
-/// 設置 Web URL
+/// 設置 Web URL 並即時導航主視窗
 #[tauri::command]
-fn set_web_url(url: String, _state: tauri::State<AppState>) {
-    // 注意：這裡需要使用內部可變性，但 tauri::State 不支援
-    // 實際實現中可能需要使用 Mutex 或其他同步原語
+fn set_web_url(url: String, app: tauri::AppHandle, state: tauri::State<AppState>) -> Result<(), String> {
     println!("設置 Web URL: {}", url);
+
+    // 先驗證 URL，解析失敗就不污染狀態
+    let parsed: tauri::Url = url.parse().map_err(|e| format!("無法解析 URL: {}", e))?;
+
+    // 驗證通過後才持久化到狀態
+    {
+        let mut web_url = state.web_url.lock().unwrap();
+        *web_url = url.clone();
+    }
+
+    // 即時導航主視窗到新的 URL
+    if let Some(window) = app.get_webview_window("main") {
+        window.navigate(parsed).map_err(|e| e.to_string())?;
+    }
+
+    Ok(())
 }
 
 /// 檢查是否為桌面模式
 #[tauri::command]
 fn is_desktop_mode(state: tauri::State<AppState>) -> bool {
-    state.desktop_mode
+    state.desktop_mode.load(Ordering::SeqCst)
 }
 
 /// 設置桌面模式
 #[tauri::command]
-fn set_desktop_mode(enabled: bool, _state: tauri::State<AppState>) {
+fn set_desktop_mode(enabled: bool, state: tauri::State<AppState>) {
     println!("設置桌面模式: {}", enabled);
+    state.desktop_mode.store(enabled, Ordering::SeqCst);
+}
+
+/// 從全局狀態取得已儲存的 `AppHandle`
+///
+/// 在 `setup` 之前呼叫（應用程式尚未初始化）會回傳 Python 端的
+/// `RuntimeError`，方便嵌入的 Python 程式碼處理錯誤。
+fn app_handle() -> PyResult<tauri::AppHandle> {
+    APP_STATE
+        .lock()
+        .unwrap()
+        .clone()
+        .ok_or_else(|| pyo3::exceptions::PyRuntimeError::new_err("應用程式尚未初始化"))
 }
 
 /// PyO3 模組定義
@@ -77,6 +189,97 @@ fn set_desktop_mode(enabled: bool, _state: tauri::State<AppState>) {
 #[pyo3(name = "ext_mod")]
 pub mod ext_mod {
     use super::*;
+    use tauri::{Emitter, Listener, WebviewUrl, WebviewWindowBuilder};
+    use tauri_plugin_shell::ShellExt;
+
+    /// 視窗設定，用於從 Python 以設定驅動的方式建立視窗
+    #[pyclass]
+    #[derive(Clone)]
+    struct WindowConfig {
+        #[pyo3(get, set)]
+        label: String,
+        #[pyo3(get, set)]
+        title: String,
+        #[pyo3(get, set)]
+        url: String,
+        #[pyo3(get, set)]
+        width: f64,
+        #[pyo3(get, set)]
+        height: f64,
+        #[pyo3(get, set)]
+        min_width: Option<f64>,
+        #[pyo3(get, set)]
+        min_height: Option<f64>,
+        #[pyo3(get, set)]
+        x: Option<f64>,
+        #[pyo3(get, set)]
+        y: Option<f64>,
+        #[pyo3(get, set)]
+        center: bool,
+        #[pyo3(get, set)]
+        resizable: bool,
+        #[pyo3(get, set)]
+        decorations: bool,
+        #[pyo3(get, set)]
+        always_on_top: bool,
+        #[pyo3(get, set)]
+        visible: bool,
+    }
+
+    #[pymethods]
+    impl WindowConfig {
+        #[new]
+        #[pyo3(signature = (
+            label,
+            url,
+            title = String::new(),
+            width = 800.0,
+            height = 600.0,
+            min_width = None,
+            min_height = None,
+            x = None,
+            y = None,
+            center = false,
+            resizable = true,
+            decorations = true,
+            always_on_top = false,
+            visible = true,
+        ))]
+        #[allow(clippy::too_many_arguments)]
+        fn new(
+            label: String,
+            url: String,
+            title: String,
+            width: f64,
+            height: f64,
+            min_width: Option<f64>,
+            min_height: Option<f64>,
+            x: Option<f64>,
+            y: Option<f64>,
+            center: bool,
+            resizable: bool,
+            decorations: bool,
+            always_on_top: bool,
+            visible: bool,
+        ) -> Self {
+            WindowConfig {
+                label,
+                title,
+                url,
+                width,
+                height,
+                min_width,
+                min_height,
+                x,
+                y,
+                center,
+                resizable,
+                decorations,
+                always_on_top,
+                visible,
+            }
+        }
+    }
 
     #[pymodule_init]
     fn init(module: &Bound<'_, PyModule>) -> PyResult<()> {
@@ -89,6 +292,26 @@ pub mod ext_mod {
         // 註冊 run_app 函數
         module.add_function(wrap_pyfunction!(run_app, module)?)?;
 
+        // 註冊 shutdown 函數
+        module.add_function(wrap_pyfunction!(shutdown, module)?)?;
+
+        // 註冊多視窗管理 API
+        module.add_class::<WindowConfig>()?;
+        module.add_function(wrap_pyfunction!(create_window, module)?)?;
+        module.add_function(wrap_pyfunction!(close_window, module)?)?;
+        module.add_function(wrap_pyfunction!(list_windows, module)?)?;
+
+        // 註冊系統匣開關與標籤設定
+        module.add_function(wrap_pyfunction!(set_tray_enabled, module)?)?;
+        module.add_function(wrap_pyfunction!(set_tray_labels, module)?)?;
+
+        // 註冊事件橋接 API
+        module.add_function(wrap_pyfunction!(emit_event, module)?)?;
+        module.add_function(wrap_pyfunction!(register_event_listener, module)?)?;
+
+        // 註冊 sidecar 行程管理
+        module.add_function(wrap_pyfunction!(start_sidecar, module)?)?;
+
         Ok(())
     }
 
@@ -109,24 +332,228 @@ pub mod ext_mod {
     }
 
     /// 運行 Tauri 應用程式
+    ///
+    /// 將 `web_url` 寫入 `AppState`，接著接管真正的事件迴圈直到結束，
+    /// 並在迴圈就緒（`RunEvent::Ready`）時將主視窗導航到 `web_url`。阻塞期間會釋放 GIL，讓 Python 其他執行緒（例如
+    /// 透過 [`shutdown`] 請求關閉）得以繼續運作。
     #[pyfunction]
-    fn run_app(web_url: String) -> PyResult<i32> {
+    fn run_app(py: Python<'_>, web_url: String) -> PyResult<i32> {
         println!("正在啟動 Tauri 應用程式，Web URL: {}", web_url);
 
-        // 創建並運行 Tauri 應用程式
-        let _builder = create_tauri_builder();
-        let _context = tauri_generate_context();
-
-        // 在實際實現中，這裡需要處理異步運行
-        // 目前返回成功狀態
-        match std::thread::spawn(move || {
-            // 這裡應該運行 Tauri 應用程式
-            // builder.run(context)
-            println!("Tauri 應用程式線程已啟動");
-            0
-        }).join() {
-            Ok(code) => Ok(code),
-            Err(_) => Ok(1),
+        let builder = create_tauri_builder();
+        let context = tauri_generate_context();
+
+        let app = builder
+            .build(context)
+            .map_err(|e| pyo3::exceptions::PyRuntimeError::new_err(e.to_string()))?;
+
+        // 將 web_url 寫入狀態
+        {
+            let state = app.state::<AppState>();
+            *state.web_url.lock().unwrap() = web_url.clone();
+        }
+
+        // 釋放 GIL 以執行阻塞的事件迴圈
+        py.allow_threads(move || {
+            app.run(move |app_handle, event| {
+                // 主視窗在事件迴圈執行 setup 時才建立，因此於 Ready 時導航
+                if let tauri::RunEvent::Ready = event {
+                    if let (Some(window), Ok(parsed)) = (app_handle.get_webview_window("main"), web_url.parse()) {
+                        let _ = window.navigate(parsed);
+                    }
+                }
+
+                // 同時處理 ExitRequested 與終端的 Exit，確保無論從哪條
+                // 路徑（tray quit、shutdown、視窗關閉）結束都會終止 sidecar
+                if let tauri::RunEvent::ExitRequested { .. } | tauri::RunEvent::Exit = event {
+                    println!("正在關閉應用程式，終止 sidecar...");
+                    kill_sidecar();
+                }
+            });
+        });
+
+        Ok(0)
+    }
+
+    /// 請求優雅關閉應用程式
+    #[pyfunction]
+    #[pyo3(signature = (code = 0))]
+    fn shutdown(code: i32) -> PyResult<()> {
+        let app = app_handle()?;
+        // 先終止 sidecar，再結束應用程式
+        kill_sidecar();
+        app.exit(code);
+        Ok(())
+    }
+
+    /// 依設定建立一個新的 Webview 視窗
+    #[pyfunction]
+    fn create_window(config: WindowConfig) -> PyResult<()> {
+        let app = app_handle()?;
+
+        let url = config
+            .url
+            .parse()
+            .map_err(|e| pyo3::exceptions::PyValueError::new_err(format!("無法解析 URL: {}", e)))?;
+
+        let mut builder = WebviewWindowBuilder::new(&app, &config.label, WebviewUrl::External(url))
+            .title(&config.title)
+            .inner_size(config.width, config.height)
+            .resizable(config.resizable)
+            .decorations(config.decorations)
+            .always_on_top(config.always_on_top)
+            .visible(config.visible);
+
+        if let (Some(min_width), Some(min_height)) = (config.min_width, config.min_height) {
+            builder = builder.min_inner_size(min_width, min_height);
+        }
+        if let (Some(x), Some(y)) = (config.x, config.y) {
+            builder = builder.position(x, y);
+        }
+        if config.center {
+            builder = builder.center();
+        }
+
+        builder
+            .build()
+            .map_err(|e| pyo3::exceptions::PyRuntimeError::new_err(e.to_string()))?;
+
+        Ok(())
+    }
+
+    /// 關閉指定 label 的視窗
+    #[pyfunction]
+    fn close_window(label: String) -> PyResult<bool> {
+        let app = app_handle()?;
+        match app.get_webview_window(&label) {
+            Some(window) => {
+                window
+                    .close()
+                    .map_err(|e| pyo3::exceptions::PyRuntimeError::new_err(e.to_string()))?;
+                Ok(true)
+            }
+            None => Ok(false),
         }
     }
+
+    /// 列出目前所有視窗的 label
+    #[pyfunction]
+    fn list_windows() -> PyResult<Vec<String>> {
+        let app = app_handle()?;
+        Ok(app.webview_windows().keys().cloned().collect())
+    }
+
+    /// 啟用或停用系統匣
+    ///
+    /// 啟用時會立即建立系統匣圖示；停用則移除已存在的圖示。
+    #[pyfunction]
+    fn set_tray_enabled(enabled: bool) -> PyResult<()> {
+        let app = app_handle()?;
+        let state = app.state::<AppState>();
+        state.enable_tray.store(enabled, Ordering::SeqCst);
+
+        if enabled {
+            setup_tray(&app, &state.tray_labels.lock().unwrap())
+                .map_err(|e| pyo3::exceptions::PyRuntimeError::new_err(e.to_string()))?;
+        } else {
+            app.remove_tray_by_id("main-tray");
+        }
+
+        Ok(())
+    }
+
+    /// 設定系統匣選單的文字標籤
+    ///
+    /// 須在啟用系統匣（[`set_tray_enabled`]）之前呼叫，寫入的標籤才會
+    /// 反映到後續建立的選單。
+    #[pyfunction]
+    fn set_tray_labels(show_hide: String, reload: String, quit: String) -> PyResult<()> {
+        let app = app_handle()?;
+        let mut labels = app.state::<AppState>().tray_labels.lock().unwrap();
+        *labels = TrayLabels {
+            show_hide,
+            reload,
+            quit,
+        };
+        Ok(())
+    }
+
+    /// 由 Python 向前端發送一個 Tauri 事件
+    #[pyfunction]
+    fn emit_event(event: String, payload: String) -> PyResult<()> {
+        let app = app_handle()?;
+        app.emit(&event, payload)
+            .map_err(|e| pyo3::exceptions::PyRuntimeError::new_err(e.to_string()))?;
+        Ok(())
+    }
+
+    /// 註冊一個前端事件的監聽器，將事件 payload 轉發回 Python
+    ///
+    /// `AppHandle::listen` 在 `setup` 之後即可使用，因此可在執行期
+    /// 隨時註冊；回呼會在事件觸發時以事件 payload 字串呼叫。
+    #[pyfunction]
+    fn register_event_listener(event: String, callback: PyObject) -> PyResult<()> {
+        let app = app_handle()?;
+        app.listen(event, move |ev| {
+            let payload = ev.payload().to_string();
+            Python::with_gil(|py| {
+                if let Err(err) = callback.call1(py, (payload,)) {
+                    err.print(py);
+                }
+            });
+        });
+        Ok(())
+    }
+
+    /// 啟動並監督一個 bundled sidecar 行程（MCP/feedback 伺服器）
+    ///
+    /// 透過 shell plugin 的 `Command::new_sidecar` 生成行程，串流其
+    /// stdout/stderr，並把子行程句柄存入全局狀態，讓 [`shutdown`] 或
+    /// 應用程式結束時一併終止。
+    #[pyfunction]
+    #[pyo3(signature = (name, args = Vec::new()))]
+    fn start_sidecar(name: String, args: Vec<String>) -> PyResult<()> {
+        use tauri_plugin_shell::process::CommandEvent;
+
+        let app = app_handle()?;
+
+        let (mut rx, child) = app
+            .shell()
+            .sidecar(&name)
+            .map_err(|e| pyo3::exceptions::PyRuntimeError::new_err(e.to_string()))?
+            .args(args)
+            .spawn()
+            .map_err(|e| pyo3::exceptions::PyRuntimeError::new_err(e.to_string()))?;
+
+        // 先終止舊的 sidecar，再以新的世代編號記錄子行程句柄
+        kill_sidecar();
+        let generation = SIDECAR_GEN.fetch_add(1, Ordering::SeqCst) + 1;
+        *SIDECAR.lock().unwrap() = Some((generation, child));
+
+        // 串流 stdout/stderr，並在行程結束時清除句柄
+        tauri::async_runtime::spawn(async move {
+            while let Some(event) = rx.recv().await {
+                match event {
+                    CommandEvent::Stdout(line) => {
+                        println!("[sidecar] {}", String::from_utf8_lossy(&line));
+                    }
+                    CommandEvent::Stderr(line) => {
+                        eprintln!("[sidecar] {}", String::from_utf8_lossy(&line));
+                    }
+                    CommandEvent::Terminated(_) => {
+                        // 僅在全局仍持有本行程（世代相符）時才清除，
+                        // 避免舊行程的結束事件清掉已重啟的新 sidecar
+                        let mut slot = SIDECAR.lock().unwrap();
+                        if matches!(*slot, Some((gen, _)) if gen == generation) {
+                            *slot = None;
+                        }
+                        break;
+                    }
+                    _ => {}
+                }
+            }
+        });
+
+        Ok(())
+    }
 }
@@ -3,39 +3,124 @@
 
 use tauri::{Builder, Manager};
 use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
 
 // 全局狀態管理
 static APP_STATE: Mutex<Option<tauri::AppHandle>> = Mutex::new(None);
 
 /// Tauri 應用程式狀態
+///
+/// 由於 `tauri::State<AppState>` 是不可變借用，欄位改用內部可變性
+/// （`Mutex` / `AtomicBool`），讓指令可以在執行期更新狀態。
 #[derive(Default)]
 struct AppState {
-    web_url: String,
-    desktop_mode: bool,
+    web_url: Mutex<String>,
+    desktop_mode: AtomicBool,
+    enable_tray: AtomicBool,
+    tray_labels: Mutex<TrayLabels>,
+}
+
+/// 系統匣選單的文字標籤
+struct TrayLabels {
+    show_hide: String,
+    reload: String,
+    quit: String,
+}
+
+impl Default for TrayLabels {
+    fn default() -> Self {
+        TrayLabels {
+            show_hide: "顯示/隱藏".to_string(),
+            reload: "重新載入".to_string(),
+            quit: "結束".to_string(),
+        }
+    }
+}
+
+/// 建立系統匣圖示與選單
+///
+/// 選單包含「顯示/隱藏」（切換 `main` 視窗可見性）、「重新載入」
+/// （重新導航到已儲存的 `web_url`）與「結束」三個項目。
+fn setup_tray(app: &tauri::AppHandle, labels: &TrayLabels) -> tauri::Result<()> {
+    use tauri::menu::{Menu, MenuItem};
+    use tauri::tray::TrayIconBuilder;
+
+    // 已存在同 id 的系統匣時不重複建立，保持冪等
+    if app.tray_by_id("main-tray").is_some() {
+        return Ok(());
+    }
+
+    let show_hide = MenuItem::with_id(app, "show_hide", &labels.show_hide, true, None::<&str>)?;
+    let reload = MenuItem::with_id(app, "reload", &labels.reload, true, None::<&str>)?;
+    let quit = MenuItem::with_id(app, "quit", &labels.quit, true, None::<&str>)?;
+    let menu = Menu::with_items(app, &[&show_hide, &reload, &quit])?;
+
+    TrayIconBuilder::with_id("main-tray")
+        .menu(&menu)
+        .on_menu_event(|app, event| match event.id.as_ref() {
+            "show_hide" => {
+                if let Some(window) = app.get_webview_window("main") {
+                    if window.is_visible().unwrap_or(false) {
+                        let _ = window.hide();
+                    } else {
+                        let _ = window.show();
+                        let _ = window.set_focus();
+                    }
+                }
+            }
+            "reload" => {
+                let url = app.state::<AppState>().web_url.lock().unwrap().clone();
+                if let (Some(window), Ok(parsed)) = (app.get_webview_window("main"), url.parse()) {
+                    let _ = window.navigate(parsed);
+                }
+            }
+            "quit" => app.exit(0),
+            _ => {}
+        })
+        .build(app)?;
+
+    Ok(())
 }
 
 /// 獲取 Web URL
 #[tauri::command]
 fn get_web_url(state: tauri::State<AppState>) -> String {
-    state.web_url.clone()
+    state.web_url.lock().unwrap().clone()
 }
 
-/// 設置 Web URL
+/// 設置 Web URL 並即時導航主視窗
 #[tauri::command]
-fn set_web_url(url: String, _state: tauri::State<AppState>) {
+fn set_web_url(url: String, app: tauri::AppHandle, state: tauri::State<AppState>) -> Result<(), String> {
     println!("設置 Web URL: {}", url);
+
+    // 先驗證 URL，解析失敗就不污染狀態
+    let parsed: tauri::Url = url.parse().map_err(|e| format!("無法解析 URL: {}", e))?;
+
+    // 驗證通過後才持久化到狀態
+    {
+        let mut web_url = state.web_url.lock().unwrap();
+        *web_url = url.clone();
+    }
+
+    // 即時導航主視窗到新的 URL
+    if let Some(window) = app.get_webview_window("main") {
+        window.navigate(parsed).map_err(|e| e.to_string())?;
+    }
+
+    Ok(())
 }
 
 /// 檢查是否為桌面模式
 #[tauri::command]
 fn is_desktop_mode(state: tauri::State<AppState>) -> bool {
-    state.desktop_mode
+    state.desktop_mode.load(Ordering::SeqCst)
 }
 
 /// 設置桌面模式
 #[tauri::command]
-fn set_desktop_mode(enabled: bool, _state: tauri::State<AppState>) {
+fn set_desktop_mode(enabled: bool, state: tauri::State<AppState>) {
     println!("設置桌面模式: {}", enabled);
+    state.desktop_mode.store(enabled, Ordering::SeqCst);
 }
 
 fn main() {
@@ -55,10 +140,18 @@ fn main() {
                 *state = Some(app.handle().clone());
             }
 
+            // 預設為桌面二進位啟用系統匣
+            let app_state = app.state::<AppState>();
+            app_state.enable_tray.store(true, Ordering::SeqCst);
+            setup_tray(app.handle(), &app_state.tray_labels.lock().unwrap())?;
+
             // 檢查是否有 MCP_WEB_URL 環境變數
             if let Ok(web_url) = std::env::var("MCP_WEB_URL") {
                 println!("檢測到 Web URL: {}", web_url);
 
+                // 持久化到狀態，讓 get_web_url 在啟動後也能取得
+                *app.state::<AppState>().web_url.lock().unwrap() = web_url.clone();
+
                 // 獲取主視窗並導航到 Web URL
                 if let Some(window) = app.get_webview_window("main") {
                     let _ = window.navigate(web_url.parse().unwrap());